@@ -15,9 +15,11 @@
  */
 use std::clone::Clone;
 use std::cmp::Eq;
-use std::collections::HashSet;
-use std::hash::Hash;
-use std::iter::Iterator;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{FromIterator, Iterator};
+use std::ops::{BitAnd, BitOr, BitXor, Sub};
 
 /// A hash set that remembers the last key it returned with its iterator
 /// it will wrap around and only return all of the keys once per iterator
@@ -27,36 +29,41 @@ use std::iter::Iterator;
 /// As of 0.5 and forward, the position is remembered, but the count is
 /// forgotten. This may break your app if you are depending on the old
 /// behavior.
+///
+/// Like `std::collections::HashSet`, the hashing algorithm is pluggable via
+/// the second type parameter `S`, which defaults to `RandomState`. Use
+/// `with_hasher` or `with_capacity_and_hasher` to supply your own.
 #[derive(Debug)]
-pub struct WrappingHashSet<T>
+pub struct WrappingHashSet<T, S = RandomState>
 where
     T: Eq + Hash,
 {
-    hashset: HashSet<T>,
+    index: HashMap<T, usize, S>,
     keys: Vec<T>,
     pos: usize,
 }
 
-pub struct Iter<'i, T: 'i>
+pub struct Iter<'i, T: 'i, S = RandomState>
 where
     T: Eq + Hash,
 {
-    whs: &'i mut WrappingHashSet<T>,
+    whs: &'i mut WrappingHashSet<T, S>,
     count: usize,
 }
 
-impl<'i, T> Iterator for Iter<'i, T>
+impl<'i, T, S> Iterator for Iter<'i, T, S>
 where
     T: Eq + Hash + Clone,
+    S: BuildHasher,
 {
     type Item = T;
     fn next(&mut self) -> Option<T> {
         // Wrap
-        if self.whs.pos >= self.whs.hashset.len() {
+        if self.whs.pos >= self.whs.keys.len() {
             self.whs.pos = 0;
         }
         self.count += 1;
-        if self.count > self.whs.hashset.len() {
+        if self.count > self.whs.keys.len() {
             self.count = 0;
             return None;
         }
@@ -65,42 +72,339 @@ where
     }
 }
 
-impl<T> WrappingHashSet<T>
+/// Like `Iter`, but never stops on its own -- it keeps wrapping
+/// indefinitely, advancing and persisting `pos` on every call. An
+/// empty set still terminates immediately rather than looping forever.
+pub struct Cycle<'i, T: 'i, S = RandomState>
+where
+    T: Eq + Hash,
+{
+    whs: &'i mut WrappingHashSet<T, S>,
+}
+
+impl<'i, T, S> Iterator for Cycle<'i, T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.whs.keys.is_empty() {
+            return None;
+        }
+        if self.whs.pos >= self.whs.keys.len() {
+            self.whs.pos = 0;
+        }
+        self.whs.pos += 1;
+        Some(self.whs.keys[self.whs.pos - 1].clone())
+    }
+}
+
+impl<T> WrappingHashSet<T, RandomState>
+where
+    T: Eq + Hash + Clone,
+{
+    pub fn new() -> WrappingHashSet<T, RandomState> {
+        WrappingHashSet {
+            index: HashMap::new(),
+            keys: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<T> Default for WrappingHashSet<T, RandomState>
 where
     T: Eq + Hash + Clone,
 {
-    pub fn new() -> WrappingHashSet<T> {
+    fn default() -> Self {
+        WrappingHashSet::new()
+    }
+}
+
+impl<T, S> WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    /// Creates an empty `WrappingHashSet` which will use the given hasher
+    /// to hash keys.
+    pub fn with_hasher(hasher: S) -> WrappingHashSet<T, S> {
         WrappingHashSet {
-            hashset: HashSet::new(),
+            index: HashMap::with_hasher(hasher),
             keys: Vec::new(),
             pos: 0,
         }
     }
 
-    pub fn iter<'i>(&'i mut self) -> Iter<'i, T> {
+    /// Creates an empty `WrappingHashSet` with at least the specified
+    /// capacity, using `hasher` to hash the keys.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> WrappingHashSet<T, S> {
+        WrappingHashSet {
+            index: HashMap::with_capacity_and_hasher(capacity, hasher),
+            keys: Vec::with_capacity(capacity),
+            pos: 0,
+        }
+    }
+
+    pub fn iter<'i>(&'i mut self) -> Iter<'i, T, S> {
         Iter {
             whs: self,
             count: 0,
         }
     }
 
+    /// Returns an iterator that yields elements indefinitely, wrapping
+    /// around and sharing the same persisted cursor as `iter()`.
+    /// Terminates immediately if the set is empty.
+    pub fn cycle<'i>(&'i mut self) -> Cycle<'i, T, S> {
+        Cycle { whs: self }
+    }
+
+    /// Returns exactly `n` elements, wrapping as many times as needed.
+    /// Returns fewer than `n` only if the set is empty.
+    pub fn take_wrapping(&mut self, n: usize) -> Vec<T> {
+        self.cycle().take(n).collect()
+    }
+
     pub fn insert(&mut self, key: T) -> bool {
-        if self.hashset.insert(key.clone()) {
-            self.keys.push(key);
-            return true;
+        if self.index.contains_key(&key) {
+            return false;
+        }
+        self.index.insert(key.clone(), self.keys.len());
+        self.keys.push(key);
+        true
+    }
+
+    /// Removes `key` in O(1) by swapping it with the last key in the
+    /// iteration order and truncating, rather than rebuilding the whole
+    /// key list. This moves whichever key used to be last into the
+    /// removed slot, so `pos` never needs to be decremented to account
+    /// for a shift -- only clamped if it now points past the end.
+    pub fn remove(&mut self, key: &T) -> bool {
+        let i = match self.index.remove(key) {
+            Some(i) => i,
+            None => return false,
+        };
+        let last = self.keys.len() - 1;
+        self.keys.swap_remove(i);
+        if i != last {
+            let moved = self.keys[i].clone();
+            self.index.insert(moved, i);
+        }
+        if self.pos > self.keys.len() {
+            self.pos = self.keys.len();
+        }
+        true
+    }
+
+    /// Returns a new set containing the keys in either `self` or
+    /// `other` (or both), in `self`'s order followed by `other`'s,
+    /// deduplicated. The result's cursor starts at 0.
+    pub fn union(&self, other: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        let mut result = WrappingHashSet::new();
+        for k in &self.keys {
+            result.insert(k.clone());
         }
-        return false;
+        for k in &other.keys {
+            result.insert(k.clone());
+        }
+        result
     }
 
-    pub fn remove<'b>(&mut self, key: &'b T) -> bool {
-        if self.hashset.remove(key) {
-            self.keys = Vec::new();
-            for k in self.hashset.iter() {
-                self.keys.push(k.clone())
+    /// Returns a new set containing the keys present in both `self`
+    /// and `other`, in `self`'s order. The result's cursor starts at 0.
+    pub fn intersection(&self, other: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        let mut result = WrappingHashSet::new();
+        for k in &self.keys {
+            if other.index.contains_key(k) {
+                result.insert(k.clone());
             }
-            return true;
         }
-        return false;
+        result
+    }
+
+    /// Returns a new set containing the keys in `self` that are not in
+    /// `other`, in `self`'s order. The result's cursor starts at 0.
+    pub fn difference(&self, other: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        let mut result = WrappingHashSet::new();
+        for k in &self.keys {
+            if !other.index.contains_key(k) {
+                result.insert(k.clone());
+            }
+        }
+        result
+    }
+
+    /// Returns a new set containing the keys in `self` or `other` but
+    /// not both, `self`'s non-shared keys first, in each operand's
+    /// order. The result's cursor starts at 0.
+    pub fn symmetric_difference(&self, other: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        let mut result = WrappingHashSet::new();
+        for k in &self.keys {
+            if !other.index.contains_key(k) {
+                result.insert(k.clone());
+            }
+        }
+        for k in &other.keys {
+            if !self.index.contains_key(k) {
+                result.insert(k.clone());
+            }
+        }
+        result
+    }
+}
+
+impl<T, S> BitOr<&WrappingHashSet<T, S>> for &WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Output = WrappingHashSet<T>;
+    fn bitor(self, rhs: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        self.union(rhs)
+    }
+}
+
+impl<T, S> BitAnd<&WrappingHashSet<T, S>> for &WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Output = WrappingHashSet<T>;
+    fn bitand(self, rhs: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        self.intersection(rhs)
+    }
+}
+
+impl<T, S> Sub<&WrappingHashSet<T, S>> for &WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Output = WrappingHashSet<T>;
+    fn sub(self, rhs: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        self.difference(rhs)
+    }
+}
+
+impl<T, S> BitXor<&WrappingHashSet<T, S>> for &WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Output = WrappingHashSet<T>;
+    fn bitxor(self, rhs: &WrappingHashSet<T, S>) -> WrappingHashSet<T> {
+        self.symmetric_difference(rhs)
+    }
+}
+
+impl<T, S> FromIterator<T> for WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> WrappingHashSet<T, S> {
+        let mut hs = WrappingHashSet::with_hasher(S::default());
+        hs.extend(iter);
+        hs
+    }
+}
+
+impl<T, S> Extend<T> for WrappingHashSet<T, S>
+where
+    T: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+/// Owning iterator over a `WrappingHashSet`'s keys, yielding each one
+/// exactly once in `keys` order rather than wrapping.
+pub struct IntoIter<T> {
+    inner: std::vec::IntoIter<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.inner.next()
+    }
+}
+
+impl<T, S> IntoIterator for WrappingHashSet<T, S>
+where
+    T: Eq + Hash,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter {
+            inner: self.keys.into_iter(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::WrappingHashSet;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::hash::{BuildHasher, Hash};
+
+    /// Mirrors the fields we actually need to persist. `index` is
+    /// reconstructed from `keys` on deserialize rather than serialized
+    /// itself, since it's just a derived lookup table.
+    #[derive(Serialize, Deserialize)]
+    struct Shadow<T> {
+        keys: Vec<T>,
+        pos: usize,
+    }
+
+    impl<T, S> Serialize for WrappingHashSet<T, S>
+    where
+        T: Eq + Hash + Clone + Serialize,
+        S: BuildHasher,
+    {
+        fn serialize<Se>(&self, serializer: Se) -> Result<Se::Ok, Se::Error>
+        where
+            Se: Serializer,
+        {
+            Shadow {
+                keys: self.keys.clone(),
+                pos: self.pos,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, T, S> Deserialize<'de> for WrappingHashSet<T, S>
+    where
+        T: Eq + Hash + Clone + Deserialize<'de>,
+        S: BuildHasher + Default,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let shadow = Shadow::<T>::deserialize(deserializer)?;
+            let mut hs = WrappingHashSet::with_hasher(S::default());
+            for key in shadow.keys {
+                hs.insert(key);
+            }
+            // An out-of-range pos (e.g. from hand-edited or stale JSON)
+            // is clamped rather than rejected, matching remove()'s own
+            // clamp-on-shrink behavior.
+            hs.pos = if shadow.pos > hs.keys.len() {
+                hs.keys.len()
+            } else {
+                shadow.pos
+            };
+            Ok(hs)
+        }
     }
 }
 
@@ -124,38 +428,30 @@ fn test_wrapping_hashset() {
     }
     // Now test wrap
     {
-        for i in hs.iter() {
-            assert_eq!(keys_as_found[0], i, "First Iter returns first element");
-            break;
-        }
+        let i = hs.iter().next().unwrap();
+        assert_eq!(keys_as_found[0], i, "First Iter returns first element");
     }
     {
-        for i in hs.iter() {
-            assert_eq!(
-                keys_as_found[1], i,
-                "Second Iter returns second element first"
-            );
-            break;
-        }
+        let i = hs.iter().next().unwrap();
+        assert_eq!(
+            keys_as_found[1], i,
+            "Second Iter returns second element first"
+        );
     }
     {
-        for i in hs.iter() {
-            assert_eq!(
-                keys_as_found[2], i,
-                "Third Iter returns third element first"
-            );
-            break;
-        }
+        let i = hs.iter().next().unwrap();
+        assert_eq!(
+            keys_as_found[2], i,
+            "Third Iter returns third element first"
+        );
     }
     // Now it should wrap because we have a new iterator
     {
-        for i in hs.iter() {
-            assert_eq!(
-                keys_as_found[0], i,
-                "Fourth Iter returns first element first"
-            );
-            break;
-        }
+        let i = hs.iter().next().unwrap();
+        assert_eq!(
+            keys_as_found[0], i,
+            "Fourth Iter returns first element first"
+        );
     }
     {
         let mut iter = hs.iter();
@@ -186,7 +482,7 @@ fn test_wrapping_hashset() {
         let mut j = 0;
         for i in hs.iter() {
             assert_ne!(keys_as_found[1], i, "Elements should not reappear");
-            j = j + 1;
+            j += 1;
         }
         assert_eq!(2, j, "We should only iterate the leftover elements");
     }
@@ -226,3 +522,213 @@ fn test_one_item() {
         assert_eq!(None, hsiter.next());
     }
 }
+
+#[test]
+fn test_with_hasher_uses_the_given_non_default_buildhasher() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    let mut hs: WrappingHashSet<&str, BuildHasherDefault<DefaultHasher>> =
+        WrappingHashSet::with_hasher(BuildHasherDefault::default());
+    hs.insert("a");
+    hs.insert("b");
+    let mut iter = hs.iter();
+    assert_eq!(Some("a"), iter.next());
+    assert_eq!(Some("b"), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn test_with_capacity_and_hasher_preallocates_and_still_inserts() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    let mut hs: WrappingHashSet<&str, BuildHasherDefault<DefaultHasher>> =
+        WrappingHashSet::with_capacity_and_hasher(8, BuildHasherDefault::default());
+    hs.insert("x");
+    hs.insert("y");
+    assert_eq!(Some("x"), hs.iter().next());
+    assert_eq!(Some("y"), hs.iter().next());
+}
+
+#[test]
+fn test_remove_mid_lap_does_not_repeat_last_read_key() {
+    // swap_remove(i) moves the *last* key into slot `i`; it does not
+    // shift the keys between `i` and `pos` the way `Vec::remove` would.
+    // So `pos` must never be decremented just because `i < pos`, or the
+    // cursor ends up pointing at an already-read key again.
+    let mut hs: WrappingHashSet<&str> = WrappingHashSet::new();
+    hs.insert("a");
+    hs.insert("b");
+    hs.insert("c");
+    hs.insert("d");
+    hs.insert("e");
+    {
+        // Advance the cursor past the first 3 keys.
+        let mut iter = hs.iter();
+        iter.next().unwrap();
+        iter.next().unwrap();
+        iter.next().unwrap();
+    }
+    assert_eq!(3, hs.pos);
+    // Remove a key at an index before the cursor.
+    hs.remove(&"b");
+    assert_eq!(
+        3, hs.pos,
+        "pos must not be decremented on removal of an earlier key"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip_preserves_keys_and_pos() {
+    let mut hs: WrappingHashSet<String> = WrappingHashSet::new();
+    hs.insert("a".to_string());
+    hs.insert("b".to_string());
+    hs.insert("c".to_string());
+    {
+        let mut iter = hs.iter();
+        iter.next().unwrap();
+        iter.next().unwrap();
+    }
+    let json = serde_json::to_string(&hs).unwrap();
+    let mut round_tripped: WrappingHashSet<String> = serde_json::from_str(&json).unwrap();
+    let mut iter = round_tripped.iter();
+    assert_eq!(Some("c".to_string()), iter.next());
+    assert_eq!(Some("a".to_string()), iter.next());
+    assert_eq!(Some("b".to_string()), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_deserialize_clamps_out_of_range_pos() {
+    let json = r#"{"keys":["a","b"],"pos":99}"#;
+    let hs: WrappingHashSet<String> = serde_json::from_str(json).unwrap();
+    assert_eq!(2, hs.pos);
+}
+
+#[cfg(test)]
+fn make_set(keys: &[&'static str]) -> WrappingHashSet<&'static str> {
+    let mut hs: WrappingHashSet<&'static str> = WrappingHashSet::new();
+    for k in keys {
+        hs.insert(k);
+    }
+    hs
+}
+
+#[test]
+fn test_union_dedups_and_preserves_self_then_other_order() {
+    let a = make_set(&["a", "b", "c"]);
+    let b = make_set(&["c", "d"]);
+    let mut u = a.union(&b);
+    let mut iter = u.iter();
+    assert_eq!(Some("a"), iter.next());
+    assert_eq!(Some("b"), iter.next());
+    assert_eq!(Some("c"), iter.next());
+    assert_eq!(Some("d"), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn test_intersection_keeps_only_shared_keys_in_self_order() {
+    let a = make_set(&["a", "b", "c"]);
+    let b = make_set(&["c", "a", "z"]);
+    let mut i = a.intersection(&b);
+    let mut iter = i.iter();
+    assert_eq!(Some("a"), iter.next());
+    assert_eq!(Some("c"), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn test_difference_keeps_only_self_only_keys() {
+    let a = make_set(&["a", "b", "c"]);
+    let b = make_set(&["b"]);
+    let mut d = a.difference(&b);
+    let mut iter = d.iter();
+    assert_eq!(Some("a"), iter.next());
+    assert_eq!(Some("c"), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn test_symmetric_difference_keeps_non_shared_keys_from_both() {
+    let a = make_set(&["a", "b"]);
+    let b = make_set(&["b", "c"]);
+    let mut sd = a.symmetric_difference(&b);
+    let mut iter = sd.iter();
+    assert_eq!(Some("a"), iter.next());
+    assert_eq!(Some("c"), iter.next());
+    assert_eq!(None, iter.next());
+}
+
+#[test]
+fn test_bitwise_operators_match_named_methods() {
+    let a = make_set(&["a", "b"]);
+    let b = make_set(&["b", "c"]);
+
+    let mut or_result = &a | &b;
+    let mut and_result = &a & &b;
+    let mut sub_result = &a - &b;
+    let mut xor_result = &a ^ &b;
+
+    assert_eq!(Some("a"), or_result.iter().next());
+    assert_eq!(Some("b"), and_result.iter().next());
+    assert_eq!(Some("a"), sub_result.iter().next());
+    assert_eq!(Some("a"), xor_result.iter().next());
+}
+
+#[test]
+fn test_from_iter_dedups_preserving_first_seen_order() {
+    let hs: WrappingHashSet<&str> = ["a", "b", "a", "c", "b"].into_iter().collect();
+    assert_eq!(vec!["a", "b", "c"], hs.keys);
+}
+
+#[test]
+fn test_extend_dedups_preserving_first_seen_order() {
+    let mut hs = make_set(&["a", "b"]);
+    hs.extend(["b", "c"]);
+    assert_eq!(vec!["a", "b", "c"], hs.keys);
+}
+
+#[test]
+fn test_owning_into_iter_yields_each_key_once_in_order() {
+    let hs = make_set(&["a", "b", "c"]);
+    let collected: Vec<&str> = hs.into_iter().collect();
+    assert_eq!(vec!["a", "b", "c"], collected);
+}
+
+#[test]
+fn test_cycle_wraps_past_the_end_indefinitely() {
+    let mut hs = make_set(&["a", "b"]);
+    let taken: Vec<&str> = hs.cycle().take(5).collect();
+    assert_eq!(vec!["a", "b", "a", "b", "a"], taken);
+}
+
+#[test]
+fn test_cycle_terminates_immediately_on_empty_set() {
+    let mut hs: WrappingHashSet<&str> = WrappingHashSet::new();
+    assert_eq!(None, hs.cycle().next());
+}
+
+#[test]
+fn test_take_wrapping_returns_exactly_n_elements_wrapping_as_needed() {
+    let mut hs = make_set(&["a", "b", "c"]);
+    assert_eq!(vec!["a", "b"], hs.take_wrapping(2));
+    assert_eq!(vec!["c", "a", "b", "c", "a"], hs.take_wrapping(5));
+}
+
+#[test]
+fn test_take_wrapping_on_empty_set_returns_empty() {
+    let mut hs: WrappingHashSet<&str> = WrappingHashSet::new();
+    assert_eq!(Vec::<&str>::new(), hs.take_wrapping(3));
+}
+
+#[test]
+fn test_cycle_and_iter_share_the_same_persisted_cursor() {
+    let mut hs = make_set(&["a", "b", "c"]);
+    assert_eq!(Some("a"), hs.iter().next());
+    assert_eq!(Some("b"), hs.cycle().next());
+    assert_eq!(Some("c"), hs.iter().next());
+}